@@ -0,0 +1,191 @@
+//! Geometric analysis of a [`Game`]'s lines, backed by an R-tree so self-intersection checks stay
+//! close to `O(n log n)` rather than `O(n^2)` on large generated tracks (polygon rings, thousands
+//! of `extension::function_lines` segments, and so on).
+
+use std::collections::HashMap;
+
+use geo::algorithm::line_intersection::{line_intersection, LineIntersection};
+use geo::{Coord, Line as GeoLine};
+use rstar::{RTree, RTreeObject, AABB};
+
+use crate::{Coordinates, Game, Line};
+
+/// A track line indexed into the R-tree by its bounding box.
+struct IndexedLine {
+    index: usize,
+    geo_line: GeoLine<f64>,
+}
+
+impl RTreeObject for IndexedLine {
+    type Envelope = AABB<[f64; 2]>;
+
+    fn envelope(&self) -> Self::Envelope {
+        let start = self.geo_line.start;
+        let end = self.geo_line.end;
+        AABB::from_corners([start.x.min(end.x), start.y.min(end.y)], [start.x.max(end.x), start.y.max(end.y)])
+    }
+}
+
+impl Line {
+    fn to_geo(self) -> GeoLine<f64> {
+        GeoLine::new(Coord { x: self.x1, y: self.y1 }, Coord { x: self.x2, y: self.y2 })
+    }
+}
+
+impl Game {
+    /// Finds every pair of lines in the track whose segments genuinely cross -- not lines that
+    /// merely touch at a shared endpoint, which is how any chain of connected lines (every
+    /// `extension::polygon_lines`/`function_lines`/`generate_track` output) meets its neighbours
+    /// -- along with the crossing point. Candidate pairs are narrowed down via an R-tree over
+    /// line bounding boxes rather than checking every pair directly.
+    pub fn find_intersections(&self) -> Vec<(usize, usize, Coordinates)> {
+        let indexed: Vec<IndexedLine> = self
+            .lines
+            .iter()
+            .enumerate()
+            .map(|(index, line)| IndexedLine { index, geo_line: line.to_geo() })
+            .collect();
+        let tree = RTree::bulk_load(indexed);
+
+        let mut intersections = Vec::new();
+        for candidate in tree.iter() {
+            for other in tree.locate_in_envelope_intersecting(&candidate.envelope()) {
+                if other.index <= candidate.index {
+                    continue;
+                }
+
+                if let Some(LineIntersection::SinglePoint { intersection, is_proper: true }) = line_intersection(candidate.geo_line, other.geo_line) {
+                    intersections.push((candidate.index, other.index, Coordinates { x: intersection.x, y: intersection.y }));
+                }
+            }
+        }
+
+        intersections
+    }
+
+    /// Subdivides every crossing line at its intersection point(s), preserving `kind`, `flipped`
+    /// and the extension flags. Useful for cleaning up self-overlapping output from
+    /// `extension::thick_polygon_lines`. Lines that weren't split keep their existing `id`; only
+    /// the newly-created segments are assigned fresh ids, continuing from the highest one seen
+    /// (mirroring `add_line`), so ids a loaded track relies on elsewhere aren't disturbed.
+    pub fn split_at_intersections(&mut self) {
+        let intersections = self.find_intersections();
+        if intersections.is_empty() {
+            return;
+        }
+
+        let mut splits: HashMap<usize, Vec<Coordinates>> = HashMap::new();
+        for (first, second, point) in intersections {
+            splits.entry(first).or_default().push(point);
+            splits.entry(second).or_default().push(point);
+        }
+
+        let mut next_id = self.lines.iter().filter_map(|line| line.id).max().unwrap_or(0);
+
+        let mut new_lines = Vec::with_capacity(self.lines.len());
+        for (index, line) in self.lines.iter().enumerate() {
+            let Some(points) = splits.get(&index) else {
+                new_lines.push(*line);
+                continue;
+            };
+
+            // Walk the split points in order along the line, from (x1, y1) to (x2, y2).
+            let mut points = points.clone();
+            points.sort_by(|a, b| {
+                let distance_to = |point: &Coordinates| (point.x - line.x1).hypot(point.y - line.y1);
+                distance_to(a).partial_cmp(&distance_to(b)).unwrap()
+            });
+
+            let mut start = Coordinates { x: line.x1, y: line.y1 };
+            for point in points {
+                next_id += 1;
+                new_lines.push(Line { id: Some(next_id), x1: start.x, y1: start.y, x2: point.x, y2: point.y, ..*line });
+                start = point;
+            }
+            next_id += 1;
+            new_lines.push(Line { id: Some(next_id), x1: start.x, y1: start.y, x2: line.x2, y2: line.y2, ..*line });
+        }
+
+        self.lines = new_lines;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Game, Line};
+
+    fn line(id: usize, x1: f64, y1: f64, x2: f64, y2: f64) -> Line {
+        Line { id: Some(id), kind: 0, x1, y1, x2, y2, flipped: false, left_extended: false, right_extended: false }
+    }
+
+    #[test]
+    fn finds_a_single_x_crossing() {
+        let mut game = Game::new();
+        game.add_line(&line(0, 0.0, 0.0, 10.0, 10.0));
+        game.add_line(&line(0, 0.0, 10.0, 10.0, 0.0));
+
+        let intersections = game.find_intersections();
+
+        assert_eq!(intersections.len(), 1);
+        let (first, second, point) = intersections[0];
+        assert_eq!((first, second), (0, 1));
+        assert!((point.x - 5.0).abs() < 1e-9);
+        assert!((point.y - 5.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn splits_an_x_crossing_into_four_segments_and_preserves_untouched_ids() {
+        let mut game = Game::new();
+        game.add_line(&line(0, 0.0, 0.0, 10.0, 10.0));
+        game.add_line(&line(0, 0.0, 10.0, 10.0, 0.0));
+        game.add_line(&line(0, 100.0, 100.0, 110.0, 100.0));
+
+        game.split_at_intersections();
+
+        assert_eq!(game.lines.len(), 5);
+
+        // The untouched third line keeps the id `add_line` originally gave it.
+        let untouched: Vec<&Line> = game.lines.iter().filter(|line| (line.x1, line.y1) == (100.0, 100.0)).collect();
+        assert_eq!(untouched.len(), 1);
+        assert_eq!(untouched[0].id, Some(3));
+
+        // The four split segments get fresh ids continuing on from the highest existing one.
+        let mut split_ids: Vec<usize> = game
+            .lines
+            .iter()
+            .filter(|line| (line.x1, line.y1) != (100.0, 100.0))
+            .filter_map(|line| line.id)
+            .collect();
+        split_ids.sort_unstable();
+        assert_eq!(split_ids, vec![4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn parallel_lines_never_intersect() {
+        let mut game = Game::new();
+        game.add_line(&line(0, 0.0, 0.0, 10.0, 0.0));
+        game.add_line(&line(0, 0.0, 1.0, 10.0, 1.0));
+
+        assert!(game.find_intersections().is_empty());
+    }
+
+    #[test]
+    fn a_chain_of_lines_sharing_endpoints_is_not_flagged_as_intersecting() {
+        let mut game = Game::new();
+        game.add_lines(
+            crate::extension::polygon_lines(6, 50, None, None, 0)
+                .iter(),
+        );
+
+        assert!(game.find_intersections().is_empty());
+    }
+
+    #[test]
+    fn two_lines_sharing_just_one_endpoint_are_not_flagged_as_intersecting() {
+        let mut game = Game::new();
+        game.add_line(&line(0, 0.0, 0.0, 10.0, 0.0));
+        game.add_line(&line(0, 10.0, 0.0, 10.0, 10.0));
+
+        assert!(game.find_intersections().is_empty());
+    }
+}
@@ -4,17 +4,21 @@
 use std::fmt::Debug;
 use std::fs;
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use svg::node::element::path::Data;
+use svg::node::element::{Circle, Element, Path};
+use svg::node::Node;
+use svg::Document;
 
 /// Coordinate system to represent vectors.
-#[derive(Default, Serialize, Debug, Copy, Clone)]
+#[derive(Default, Serialize, Deserialize, Debug, Copy, Clone)]
 pub struct Coordinates {
     x: f64,
     y: f64,
 }
 
 /// Riders (characters with snowboards) with some starting position/velocity.
-#[derive(Default, Serialize, Debug, Copy, Clone)]
+#[derive(Default, Serialize, Deserialize, Debug, Copy, Clone)]
 pub struct Rider {
     #[serde(rename = "startPosition")]
     start_position: Coordinates,
@@ -24,7 +28,7 @@ pub struct Rider {
 }
 
 /// Layers on the game.
-#[derive(Default, Serialize, Debug)]
+#[derive(Default, Serialize, Deserialize, Debug)]
 pub struct Layer {
     id: usize,
     name: String,
@@ -46,7 +50,7 @@ impl Layer {
 
 /// Single line representation representation that stretches from (`x1`, `y1`) to (`x2`, `y2`)
 /// on the 2D coordinate system of the game; and of type `kind`.
-#[derive(Default, Serialize, Debug, Copy, Clone)]
+#[derive(Default, Serialize, Deserialize, Debug, Copy, Clone)]
 pub struct Line {
     /// Game requires (unique) id for every line -- we make this an Option type so that the game handles the
     /// enumeration of lines passed to it
@@ -66,7 +70,7 @@ pub struct Line {
 }
 
 /// Version used for game.
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct Version(String);
 
 /// Crate only tested version for default game -- can override.
@@ -77,7 +81,7 @@ impl Default for Version {
 }
 
 /// Main representation of a Line Rider game.
-#[derive(Default, Serialize, Debug)]
+#[derive(Default, Serialize, Deserialize, Debug)]
 pub struct Game {
     label: String,
     creator: String,
@@ -104,10 +108,26 @@ impl Game {
         }
     }
 
+    /// Parses a `Game` from its JSON representation, e.g. one previously produced by
+    /// `construct_game`. Lets users load an existing exported track, append
+    /// `extension`-generated lines/riders, and re-export.
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+
+    /// Reads a `Game` from a JSON file on disk, as written by `write_to_file`.
+    pub fn from_file(filename: &str) -> std::io::Result<Self> {
+        let contents = fs::read_to_string(filename)?;
+        Self::from_json(&contents).map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))
+    }
+
     /// Add a singular line to the game.
     pub fn add_line(&mut self, line: &Line) {
+        // Loaded tracks may already have concrete, non-contiguous ids, so continue numbering from
+        // the highest one seen rather than assuming `lines.len()` is the next free id.
+        let next_id = self.lines.iter().filter_map(|line| line.id).max().unwrap_or(0) + 1;
         let line_with_id = Line {
-            id: Some(self.lines.len() + 1),
+            id: Some(next_id),
             ..*line
         };
         self.lines.push(line_with_id);
@@ -142,8 +162,112 @@ impl Game {
         fs::write(filename, self.construct_game())?;
         Ok(())
     }
+
+    /// Renders a zero-dependency-on-the-game SVG preview of the track to `filename`, so it can be
+    /// eyeballed without importing it. Every `Line` is drawn as a `<path>` colored by `kind`, each
+    /// `Rider`'s start position is drawn as a circle, and an `<animateMotion>` slides a marker
+    /// along the rider's initial velocity so its starting trajectory is visible at a glance.
+    pub fn write_to_svg(&self, filename: &str) -> std::io::Result<()> {
+        // The game's Y axis grows upward; SVG's grows downward.
+        let flip_y = |y: f64| -y;
+
+        let mut min_x = f64::INFINITY;
+        let mut max_x = f64::NEG_INFINITY;
+        let mut min_y = f64::INFINITY;
+        let mut max_y = f64::NEG_INFINITY;
+
+        for line in &self.lines {
+            for (x, y) in [(line.x1, line.y1), (line.x2, line.y2)] {
+                min_x = min_x.min(x);
+                max_x = max_x.max(x);
+                min_y = min_y.min(y);
+                max_y = max_y.max(y);
+            }
+        }
+
+        if !min_x.is_finite() {
+            min_x = 0.0;
+            max_x = 0.0;
+            min_y = 0.0;
+            max_y = 0.0;
+        }
+
+        let track_extent = (max_x - min_x).max(max_y - min_y);
+
+        // Leave a little breathing room around the track.
+        let margin = (track_extent * 0.05).max(1.0);
+
+        let mut document = Document::new().set(
+            "viewBox",
+            (
+                min_x - margin,
+                flip_y(max_y) - margin,
+                max_x - min_x + 2.0 * margin,
+                max_y - min_y + 2.0 * margin,
+            ),
+        );
+
+        for line in &self.lines {
+            let color = match line.kind {
+                0 => "blue",
+                1 => "red",
+                2 => "green",
+                _ => "black",
+            };
+            let data = Data::new()
+                .move_to((line.x1, flip_y(line.y1)))
+                .line_to((line.x2, flip_y(line.y2)));
+            let path = Path::new()
+                .set("fill", "none")
+                .set("stroke", color)
+                .set("stroke-width", 0.5)
+                .set("d", data);
+            document = document.add(path);
+        }
+
+        for rider in &self.riders {
+            let start = (rider.start_position.x, flip_y(rider.start_position.y));
+
+            // Scale with the track, not the (already floor-clamped) margin, so the marker stays
+            // visible on both tiny and huge tracks.
+            let marker_radius = (track_extent * 0.01).max(0.5);
+            let marker = Circle::new()
+                .set("cx", start.0)
+                .set("cy", start.1)
+                .set("r", marker_radius)
+                .set("fill", "orange");
+            document = document.add(marker);
+
+            // Ride the starting velocity out for a second's worth of (unaccelerated) travel, just
+            // to give a sense of the rider's initial direction.
+            let end = (
+                rider.start_position.x + rider.start_velocity.x * 60.0,
+                flip_y(rider.start_position.y + rider.start_velocity.y * 60.0),
+            );
+            let motion_data = Data::new().move_to(start).line_to(end);
+
+            let mut animate_motion = Element::new("animateMotion");
+            animate_motion.assign("path", motion_data);
+            animate_motion.assign("dur", "1s");
+            animate_motion.assign("repeatCount", "indefinite");
+
+            let animated_marker = Circle::new()
+                .set("r", marker_radius * 1.5)
+                .set("fill", "darkorange")
+                .add(animate_motion);
+            document = document.add(animated_marker);
+        }
+
+        svg::save(filename, &document)
+    }
 }
 
+/// Rider trajectory simulation, to validate tracks offline.
+pub mod simulation;
+
+/// Geometric analysis of a game's lines, e.g. self-intersection detection.
+pub mod geometry;
+
 /// Extension definitions and functions to create Line Rider maps with.
 pub mod extension {
     use std::ops::Range;
@@ -306,4 +430,215 @@ pub mod extension {
 
         function_lines
     }
+
+    /// Guards `adaptive_function_lines`'s recursion against pathological functions/tolerances.
+    const ADAPTIVE_MAX_DEPTH: u8 = 24;
+
+    /// Creates and returns lines to sketch out a function `func` over `range`, subdividing only
+    /// where the curve needs it: flat stretches get a single long line, sharp bends get several
+    /// short ones. At each candidate segment `[a, b]`, `func`'s midpoint is compared against the
+    /// straight-line interpolation between its endpoints; if the vertical deviation exceeds
+    /// `tolerance`, or the segment is wider than `max_span`, it's split at the midpoint and both
+    /// halves are considered in turn. All lines created will be of type `kind`.
+    pub fn adaptive_function_lines(func: fn(f64) -> f64, range: Range<f64>, tolerance: f64, max_span: f64, kind: Option<usize>) -> Vec<Line> {
+        let mut lines = Vec::new();
+        subdivide(
+            func,
+            range.start,
+            range.end,
+            func(range.start),
+            func(range.end),
+            tolerance,
+            max_span,
+            ADAPTIVE_MAX_DEPTH,
+            kind.unwrap_or(1),
+            &mut lines,
+        );
+        lines
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn subdivide(func: fn(f64) -> f64, a: f64, b: f64, func_a: f64, func_b: f64, tolerance: f64, max_span: f64, depth: u8, kind: usize, lines: &mut Vec<Line>) {
+        let midpoint = (a + b) / 2.0;
+        let func_midpoint = func(midpoint);
+        let interpolated_midpoint = (func_a + func_b) / 2.0;
+        let deviation = (func_midpoint - interpolated_midpoint).abs();
+
+        if depth == 0 || (deviation <= tolerance && (b - a) <= max_span) {
+            lines.push(Line {
+                kind,
+                x1: a,
+                y1: func_a,
+                x2: b,
+                y2: func_b,
+                ..Line::default()
+            });
+            return;
+        }
+
+        subdivide(func, a, midpoint, func_a, func_midpoint, tolerance, max_span, depth - 1, kind, lines);
+        subdivide(func, midpoint, b, func_midpoint, func_b, tolerance, max_span, depth - 1, kind, lines);
+    }
+
+    /// Configuration for [`generate_track`]'s momentum-biased random walk.
+    #[derive(Clone, Debug)]
+    pub struct GenerateTrackConfig {
+        /// Where the walk starts.
+        pub start_position: Coordinates,
+        /// Candidate `(dx, dy)` offsets the walker may step in each iteration.
+        pub directions: Vec<(f64, f64)>,
+        /// Relative likelihood of picking each entry of `directions` (same length as `directions`).
+        pub step_weights: Vec<u32>,
+        /// Probability, in `[0, 1]`, of repeating the previous step's direction instead of
+        /// drawing a new one -- higher values produce longer, smoother descents.
+        pub momentum_prob: f64,
+        /// Distance covered by a single step.
+        pub step_length: f64,
+        /// How strongly a pending waypoint's bearing is blended into the chosen direction.
+        pub waypoint_steer: f64,
+        /// Points the walk is steered towards, in order, one at a time.
+        pub waypoints: Vec<Coordinates>,
+        /// How many lines to generate.
+        pub steps: usize,
+    }
+
+    impl Default for GenerateTrackConfig {
+        fn default() -> Self {
+            GenerateTrackConfig {
+                start_position: Coordinates::default(),
+                // A spread of directions biased towards travelling right and downhill, since a
+                // downward trend is what keeps the result rideable.
+                directions: vec![(-0.5, -0.2), (0.0, -0.5), (0.5, -0.2), (1.0, -0.1), (1.0, 0.3)],
+                step_weights: vec![1, 2, 3, 3, 1],
+                momentum_prob: 0.6,
+                step_length: 2.0,
+                waypoint_steer: 0.5,
+                waypoints: Vec::new(),
+                steps: 200,
+            }
+        }
+    }
+
+    /// Picks an index from `weights` with probability proportional to its weight.
+    fn weighted_choice(weights: &[u32]) -> usize {
+        let total: u32 = weights.iter().sum();
+        if total == 0 {
+            // Empty or all-zero weights: nothing to weight between, so just take the first entry.
+            return 0;
+        }
+
+        let mut roll = (rand::random::<f64>() * total as f64) as u32;
+
+        for (index, weight) in weights.iter().enumerate() {
+            if roll < *weight {
+                return index;
+            }
+            roll -= weight;
+        }
+
+        weights.len() - 1
+    }
+
+    /// Generates a continuous, connected run of `Line`s a rider can actually follow, via a
+    /// momentum-biased random walk: each step either repeats the previous direction (with
+    /// probability `momentum_prob`, for long smooth descents) or draws a fresh one from
+    /// `directions` weighted by `step_weights`, optionally nudged towards a pending waypoint.
+    pub fn generate_track(config: &GenerateTrackConfig) -> Vec<Line> {
+        let mut lines = Vec::new();
+        let mut position = config.start_position;
+        let mut direction = config
+            .directions
+            .first()
+            .copied()
+            .unwrap_or((1.0, 0.0));
+        let mut pending_waypoints = config.waypoints.clone();
+
+        for _ in 0..config.steps {
+            let mut chosen_direction = if rand::random::<f64>() < config.momentum_prob {
+                direction
+            } else {
+                let index = weighted_choice(&config.step_weights) % config.directions.len().max(1);
+                config.directions.get(index).copied().unwrap_or(direction)
+            };
+
+            if let Some(waypoint) = pending_waypoints.first() {
+                let bearing = (waypoint.x - position.x, waypoint.y - position.y);
+                let distance = bearing.0.hypot(bearing.1);
+
+                if distance < config.step_length {
+                    pending_waypoints.remove(0);
+                } else {
+                    chosen_direction.0 += bearing.0 / distance * config.waypoint_steer;
+                    chosen_direction.1 += bearing.1 / distance * config.waypoint_steer;
+                }
+            }
+
+            let magnitude = chosen_direction.0.hypot(chosen_direction.1).max(f64::EPSILON);
+            let next_position = Coordinates {
+                x: position.x + chosen_direction.0 / magnitude * config.step_length,
+                y: position.y + chosen_direction.1 / magnitude * config.step_length,
+            };
+
+            lines.push(Line {
+                id: None,
+                kind: 0,
+                x1: position.x,
+                y1: position.y,
+                x2: next_position.x,
+                y2: next_position.y,
+                flipped: false,
+                left_extended: true,
+                right_extended: true,
+            });
+
+            direction = chosen_direction;
+            position = next_position;
+        }
+
+        lines
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn adaptive_function_lines_uses_one_segment_for_a_flat_function() {
+            let lines = adaptive_function_lines(|_| 0.0, 0.0..100.0, 0.1, 1000.0, None);
+
+            assert_eq!(lines.len(), 1);
+            assert_eq!((lines[0].x1, lines[0].x2), (0.0, 100.0));
+        }
+
+        #[test]
+        fn adaptive_function_lines_subdivides_a_curved_function_under_a_tight_tolerance() {
+            let lines = adaptive_function_lines(|x| x * (10.0 - x), 0.0..10.0, 0.01, 1000.0, None);
+
+            assert!(lines.len() > 1);
+        }
+
+        #[test]
+        fn generate_track_produces_a_connected_chain_of_the_requested_length() {
+            let config = GenerateTrackConfig { steps: 20, ..GenerateTrackConfig::default() };
+
+            let lines = generate_track(&config);
+
+            assert_eq!(lines.len(), 20);
+            for pair in lines.windows(2) {
+                assert_eq!((pair[0].x2, pair[0].y2), (pair[1].x1, pair[1].y1));
+            }
+        }
+
+        #[test]
+        fn generate_track_does_not_panic_on_empty_step_weights() {
+            let config = GenerateTrackConfig {
+                step_weights: Vec::new(),
+                momentum_prob: 0.0,
+                steps: 5,
+                ..GenerateTrackConfig::default()
+            };
+
+            assert_eq!(generate_track(&config).len(), 5);
+        }
+    }
 }
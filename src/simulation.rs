@@ -0,0 +1,237 @@
+//! Offline rider-trajectory simulation, so a track can be sanity-checked without opening the game.
+//!
+//! Each rider is modelled as a point mass integrated under constant gravity. Every step, the
+//! rider's swept position is tested against nearby `Line`s; on contact the point is placed at
+//! the crossing point on the line, the velocity component into the line is cancelled, and the
+//! along-line component is damped or boosted depending on the line's `kind`, mirroring how Line
+//! Rider's own physics line types behave.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{Coordinates, Game, Line};
+
+/// Normal ("blue") lines: kill the velocity component into the line, with some sliding friction.
+/// Handled as the fallback arm of `simulate_rider`'s `match line.kind`, alongside unrecognised
+/// kinds, so this constant exists for documentation rather than being matched on directly.
+#[allow(dead_code)]
+const NORMAL_LINE: usize = 0;
+/// Acceleration ("red") lines: add a tangential impulse along the line's direction.
+const ACCELERATION_LINE: usize = 1;
+/// Scenery ("green") lines: decorative only, never collided with.
+const SCENERY_LINE: usize = 2;
+
+const GRAVITY: f64 = 0.05;
+const FRICTION: f64 = 0.02;
+const ACCELERATION_IMPULSE: f64 = 0.1;
+
+/// Uniform spatial grid over line endpoints, used to cheaply find the handful of lines near a
+/// rider instead of testing every line in the track every step (as physics engines broad-phase).
+struct LineGrid {
+    cell_size: f64,
+    cells: HashMap<(i64, i64), Vec<usize>>,
+}
+
+impl LineGrid {
+    fn build(lines: &[Line]) -> Self {
+        let cell_size = lines
+            .iter()
+            .map(|line| (line.x2 - line.x1).hypot(line.y2 - line.y1))
+            .fold(1.0_f64, f64::max);
+
+        let mut cells: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+        for (index, line) in lines.iter().enumerate() {
+            if line.kind == SCENERY_LINE {
+                continue;
+            }
+            for cell in [
+                Self::cell_of(cell_size, line.x1, line.y1),
+                Self::cell_of(cell_size, line.x2, line.y2),
+            ] {
+                cells.entry(cell).or_default().push(index);
+            }
+        }
+
+        LineGrid { cell_size, cells }
+    }
+
+    fn cell_of(cell_size: f64, x: f64, y: f64) -> (i64, i64) {
+        ((x / cell_size).floor() as i64, (y / cell_size).floor() as i64)
+    }
+
+    /// Indices of lines bucketed into the cell containing `(x, y)` or any of its neighbours.
+    /// Deduped, since a line spanning two of those cells would otherwise be bucketed -- and
+    /// returned -- more than once.
+    fn nearby(&self, x: f64, y: f64) -> HashSet<usize> {
+        let (cx, cy) = Self::cell_of(self.cell_size, x, y);
+        let mut indices = HashSet::new();
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                if let Some(bucket) = self.cells.get(&(cx + dx, cy + dy)) {
+                    indices.extend(bucket.iter().copied());
+                }
+            }
+        }
+        indices
+    }
+}
+
+/// Slack applied to `segment_intersection`'s `t`/`u` bounds. A rider resting exactly on a line
+/// reproduces its contact point with floating-point noise each step, which can tip the next
+/// step's start a hair onto the far side of the line; without this slack that reads as "started
+/// past the line" and the strict `[0, 1]` bounds reject the crossing, letting the rider sink
+/// through one step at a time.
+const INTERSECTION_EPSILON: f64 = 1e-9;
+
+/// Point where segment `p1`-`p2` (the rider's swept motion for this step) first crosses segment
+/// `a`-`b` (a track line), if it does. `None` for parallel or non-overlapping segments.
+fn segment_intersection(p1: Coordinates, p2: Coordinates, a: Coordinates, b: Coordinates) -> Option<Coordinates> {
+    let sweep = (p2.x - p1.x, p2.y - p1.y);
+    let line = (b.x - a.x, b.y - a.y);
+
+    let denominator = sweep.0 * line.1 - sweep.1 * line.0;
+    if denominator.abs() < f64::EPSILON {
+        return None;
+    }
+
+    let offset = (a.x - p1.x, a.y - p1.y);
+    let t = (offset.0 * line.1 - offset.1 * line.0) / denominator;
+    let u = (offset.0 * sweep.1 - offset.1 * sweep.0) / denominator;
+
+    let bounds = -INTERSECTION_EPSILON..=1.0 + INTERSECTION_EPSILON;
+    if bounds.contains(&t) && bounds.contains(&u) {
+        Some(Coordinates {
+            x: p1.x + t * sweep.0,
+            y: p1.y + t * sweep.1,
+        })
+    } else {
+        None
+    }
+}
+
+/// Integrates one rider's position/velocity for `steps` Euler steps, sweep-testing each step's
+/// motion against `lines` (broad-phased via `grid`), and returns the position at every step.
+fn simulate_rider(lines: &[Line], grid: &LineGrid, mut position: Coordinates, mut velocity: Coordinates, steps: usize) -> Vec<Coordinates> {
+    let mut trajectory = Vec::with_capacity(steps);
+
+    for _ in 0..steps {
+        velocity.y -= GRAVITY;
+
+        let swept_from = position;
+        let swept_to = Coordinates {
+            x: position.x + velocity.x,
+            y: position.y + velocity.y,
+        };
+
+        // A fast rider can leave its starting cell in one step, so broad-phase against both ends
+        // of the swept motion, not just where it lands.
+        let mut candidates = grid.nearby(swept_from.x, swept_from.y);
+        candidates.extend(grid.nearby(swept_to.x, swept_to.y));
+
+        let mut closest_hit: Option<(f64, Coordinates, usize)> = None;
+        for index in candidates {
+            let line = &lines[index];
+            let a = Coordinates { x: line.x1, y: line.y1 };
+            let b = Coordinates { x: line.x2, y: line.y2 };
+
+            let Some(hit) = segment_intersection(swept_from, swept_to, a, b) else {
+                continue;
+            };
+
+            let distance = (hit.x - swept_from.x).hypot(hit.y - swept_from.y);
+            let is_closer = match &closest_hit {
+                Some((best, _, _)) => distance < *best,
+                None => true,
+            };
+            if is_closer {
+                closest_hit = Some((distance, hit, index));
+            }
+        }
+
+        position = match closest_hit {
+            Some((_, hit, index)) => {
+                let line = &lines[index];
+                let a = Coordinates { x: line.x1, y: line.y1 };
+                let b = Coordinates { x: line.x2, y: line.y2 };
+
+                // Decompose velocity into along-line and perpendicular-to-line components.
+                let direction_length = (b.x - a.x).hypot(b.y - a.y);
+                let tangent = ((b.x - a.x) / direction_length, (b.y - a.y) / direction_length);
+
+                let along = velocity.x * tangent.0 + velocity.y * tangent.1;
+
+                match line.kind {
+                    ACCELERATION_LINE => {
+                        let along = along.signum() * (along.abs() + ACCELERATION_IMPULSE);
+                        velocity.x = along * tangent.0;
+                        velocity.y = along * tangent.1;
+                    }
+                    _ => {
+                        // Normal (`NORMAL_LINE`) and unrecognised lines: cancel the component into
+                        // the line, keep the rest with a little sliding friction.
+                        let along = along * (1.0 - FRICTION);
+                        velocity.x = along * tangent.0;
+                        velocity.y = along * tangent.1;
+                    }
+                }
+
+                hit
+            }
+            None => swept_to,
+        };
+
+        trajectory.push(position);
+    }
+
+    trajectory
+}
+
+/// Simulates every rider in `game` for `steps` physics steps, returning each rider's trajectory
+/// (one `Coordinates` per step). Scenery lines (`kind == 2`) are never collided with.
+pub fn simulate(game: &Game, steps: usize) -> Vec<Vec<Coordinates>> {
+    let grid = LineGrid::build(&game.lines);
+
+    game.riders
+        .iter()
+        .map(|rider| simulate_rider(&game.lines, &grid, rider.start_position, rider.start_velocity, steps))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Rider;
+
+    fn flat_line(x1: f64, x2: f64, y: f64, kind: usize) -> Line {
+        Line { id: None, kind, x1, y1: y, x2, y2: y, flipped: false, left_extended: false, right_extended: false }
+    }
+
+    #[test]
+    fn rider_lands_and_rests_on_a_known_blue_line() {
+        let mut game = Game::new();
+        game.add_line(&flat_line(-5.0, 5.0, 0.0, NORMAL_LINE));
+        game.add_rider(&Rider {
+            start_position: Coordinates { x: 0.0, y: 10.0 },
+            start_velocity: Coordinates::default(),
+            remountable: 0,
+        });
+
+        let trajectories = simulate(&game, 200);
+        let final_position = *trajectories[0].last().unwrap();
+
+        assert!(final_position.y.abs() < 0.2, "rider should be resting on the line, got y = {}", final_position.y);
+        assert!(final_position.x.abs() <= 5.0);
+    }
+
+    #[test]
+    fn grid_does_not_double_count_a_short_line_inside_a_single_cell() {
+        // `cell_size` is never below 1.0, so this 0.1-long line's two endpoints fall in the same
+        // cell and its index is bucketed there twice; `nearby` should still return it only once.
+        let lines = vec![flat_line(0.0, 0.1, 0.0, NORMAL_LINE)];
+        let grid = LineGrid::build(&lines);
+
+        let nearby = grid.nearby(0.05, 0.0);
+
+        assert_eq!(nearby.len(), 1);
+        assert!(nearby.contains(&0));
+    }
+}